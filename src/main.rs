@@ -1,7 +1,15 @@
 use std::cmp::Ordering;
 use std::collections::{BinaryHeap, HashMap};
+use std::f64::consts::SQRT_2;
 
-const GRID_SIZE: usize = 8;
+/// How a single step between two adjacent cells is priced.
+#[derive(Clone, Copy, PartialEq)]
+enum MovementMode {
+    /// Every step, orthogonal or diagonal, costs 1 (Chebyshev / Moore neighbourhood).
+    Uniform,
+    /// Orthogonal steps cost 1, diagonal steps cost √2 (true Euclidean lengths).
+    Diagonal,
+}
 
 fn calc_dist(point1: (usize, usize), point2: (usize, usize)) -> f64 {
     let x1 = point1.0 as f64;
@@ -12,9 +20,23 @@ fn calc_dist(point1: (usize, usize), point2: (usize, usize)) -> f64 {
     ((x2 - x1).powi(2) + (y2 - y1).powi(2)).sqrt()
 }
 
+/// A search state in the expanded graph: a board cell paired with the number
+/// of barrier-punching charges remaining on arrival.
+type State = ((usize, usize), usize);
+
+/// A powerup that, once collected, lets the player punch through barrier cells
+/// for a limited number of steps (`value` charges).
+#[derive(Clone, Copy, PartialEq)]
+struct Powerup {
+    position: (usize, usize),
+    value: usize,
+}
+
 #[derive(Clone, Copy, PartialEq)]
 struct Node {
     point: (usize, usize),
+    /// Barrier-punching charges remaining on arrival at `point`.
+    charges: usize,
     g_score: f64,
     h_score: f64,
 }
@@ -22,9 +44,10 @@ struct Node {
 impl Eq for Node {}
 
 impl Node {
-    fn new(point: (usize, usize), g_score: f64, h_score: f64) -> Node {
+    fn new(point: (usize, usize), charges: usize, g_score: f64, h_score: f64) -> Node {
         Node {
             point,
+            charges,
             g_score,
             h_score,
         }
@@ -37,34 +60,40 @@ impl Node {
 
 impl Ord for Node {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).unwrap()
+        // `BinaryHeap` is a max-heap, so invert the `f_score` comparison to pop
+        // the smallest `f` first, then break ties toward the larger `g_score`
+        // (the node closer to the goal), which trims expansions on open grids.
+        other
+            .f_score()
+            .partial_cmp(&self.f_score())
+            .unwrap()
+            .then_with(|| self.g_score.partial_cmp(&other.g_score).unwrap())
     }
 }
 
 impl PartialOrd for Node {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(
-            self.f_score()
-                .partial_cmp(&other.f_score())
-                .unwrap()
-                .reverse(),
-        )
+        Some(self.cmp(other))
     }
 }
 
 struct Game {
-    board: [[char; GRID_SIZE]; GRID_SIZE],
+    board: Vec<Vec<char>>,
+    rows: usize,
+    cols: usize,
     player: (usize, usize),
     destination: (usize, usize),
     barriers: Vec<(usize, usize)>,
-    powerup: (usize, usize),
-    has_powerup: bool,
+    powerup: Powerup,
+    mode: MovementMode,
 }
 
 impl Game {
     fn new() -> Game {
         Game {
-            board: [['-'; GRID_SIZE]; GRID_SIZE],
+            board: vec![vec!['-'; 8]; 8],
+            rows: 8,
+            cols: 8,
             player: (0, 0),
             destination: (4, 7),
             barriers: vec![
@@ -77,11 +106,75 @@ impl Game {
                 (6, 2),
                 (7, 2),
             ],
-            powerup: (5, 0),
-            has_powerup: false,
+            powerup: Powerup {
+                position: (5, 0),
+                value: 3,
+            },
+            mode: MovementMode::Diagonal,
         }
     }
 
+    /// Build a board from an ASCII map, one row per line. Markers: `P` player,
+    /// `D` destination, `x` barrier, `O` powerup, `-`/`.` open cell. Returns an
+    /// error when the rows are ragged, a marker is unknown, or the required `P`
+    /// and `D` markers are missing.
+    fn from_str(map: &str) -> Result<Game, String> {
+        let lines: Vec<&str> = map.lines().filter(|l| !l.trim().is_empty()).collect();
+        if lines.is_empty() {
+            return Err("empty map".to_string());
+        }
+        let cols = lines[0].chars().count();
+        let rows = lines.len();
+
+        let board = vec![vec!['-'; cols]; rows];
+        let mut player = None;
+        let mut destination = None;
+        let mut barriers = Vec::new();
+        let mut powerup = None;
+
+        for (i, line) in lines.iter().enumerate() {
+            let cells: Vec<char> = line.chars().collect();
+            if cells.len() != cols {
+                return Err(format!(
+                    "ragged map: row {} has {} cells, expected {}",
+                    i,
+                    cells.len(),
+                    cols
+                ));
+            }
+            for (j, c) in cells.into_iter().enumerate() {
+                match c {
+                    'P' => player = Some((i, j)),
+                    'D' => destination = Some((i, j)),
+                    'x' => barriers.push((i, j)),
+                    'O' => powerup = Some((i, j)),
+                    '-' | '.' => {}
+                    other => return Err(format!("unknown marker '{}' at ({}, {})", other, i, j)),
+                }
+            }
+        }
+
+        let player = player.ok_or("missing player 'P' marker")?;
+        let destination = destination.ok_or("missing destination 'D' marker")?;
+        // A powerup is optional; when absent it coincides with the destination
+        // so the via-powerup route degenerates into the direct one.
+        let powerup = Powerup {
+            position: powerup.unwrap_or(destination),
+            value: 3,
+        };
+
+        Ok(Game {
+            board,
+            rows,
+            cols,
+            player,
+            destination,
+            barriers,
+            powerup,
+            mode: MovementMode::Diagonal,
+        })
+    }
+
     fn print_board(&self) {
         for (i, row) in self.board.iter().enumerate() {
             for (j, char) in row.iter().enumerate() {
@@ -91,7 +184,7 @@ impl Game {
                     print!("x ");
                 } else if (i, j) == self.destination {
                     print!("D ");
-                } else if (i, j) == self.powerup {
+                } else if (i, j) == self.powerup.position {
                     print!("O ");
                 } else {
                     print!("{} ", char);
@@ -103,13 +196,14 @@ impl Game {
     }
 
     fn play(&mut self, instructions: Vec<(usize, usize)>) {
+        // Charges carried into each cell: collecting the powerup refills them,
+        // punching through a barrier spends one.
+        let mut charges = 0;
         for (i, j) in instructions {
-            if !self.has_powerup && self.powerup == (i, j) {
-                self.has_powerup = true;
-            }
-            if self.has_powerup {
-                // Clear barriers if the player has the powerup
-                self.barriers.clear();
+            if self.powerup.position == (i, j) {
+                charges = self.powerup.value;
+            } else if self.barriers.contains(&(i, j)) && charges > 0 {
+                charges -= 1;
             }
             self.player = (i, j);
             self.print_board();
@@ -117,7 +211,36 @@ impl Game {
         }
     }
 
-    fn get_neighbors(&self, point: (usize, usize)) -> Vec<(usize, usize)> {
+    /// Cost of a single step between two adjacent cells under the active
+    /// movement mode, before the terrain multiplier from `move_cost` is applied.
+    fn step_cost(&self, from: (usize, usize), to: (usize, usize)) -> f64 {
+        match self.mode {
+            MovementMode::Uniform => 1.0,
+            MovementMode::Diagonal => calc_dist(from, to),
+        }
+    }
+
+    /// Admissible heuristic for 8-directional movement, kept consistent with
+    /// `step_cost`: octile distance when diagonals cost √2, Chebyshev distance
+    /// when every step costs 1.
+    fn heuristic(&self, a: (usize, usize), b: (usize, usize)) -> f64 {
+        let dx = (a.0 as f64 - b.0 as f64).abs();
+        let dy = (a.1 as f64 - b.1 as f64).abs();
+        match self.mode {
+            MovementMode::Uniform => dx.max(dy),
+            MovementMode::Diagonal => (dx + dy) + (SQRT_2 - 2.0) * dx.min(dy),
+        }
+    }
+
+    fn move_cost(&self, cell: (usize, usize)) -> f64 {
+        if self.barriers.contains(&cell) {
+            100.0
+        } else {
+            1.0
+        }
+    }
+
+    fn get_neighbors(&self, point: (usize, usize), charges: usize) -> Vec<(usize, usize)> {
         let mut neighbors = Vec::new();
 
         let x = point.0 as i32;
@@ -133,12 +256,15 @@ impl Game {
                 let new_y = y + j;
 
                 // Check if the neighbor position is within the grid boundaries
-                if new_x >= 0 && new_x < GRID_SIZE as i32 && new_y >= 0 && new_y < GRID_SIZE as i32
+                if new_x >= 0
+                    && new_x < self.rows as i32
+                    && new_y >= 0
+                    && new_y < self.cols as i32
                 {
                     let neighbor = (new_x as usize, new_y as usize);
-
-                    // Check if the neighbor position is not a barrier or the player has the power-up
-                    if !self.barriers.contains(&neighbor) || self.has_powerup {
+                    // Barrier cells may only be entered while the powerup has
+                    // charges left; open cells are always available.
+                    if !self.barriers.contains(&neighbor) || charges > 0 {
                         neighbors.push(neighbor);
                     }
                 }
@@ -148,24 +274,214 @@ impl Game {
         neighbors
     }
 
+    /// Plan a path from the player to the destination, searching over the
+    /// expanded state `(point, charges_remaining)` so the solver can weigh
+    /// grabbing the powerup and punching through a barrier wall against taking
+    /// the long way around. Collecting the powerup refills the charges; each
+    /// barrier step spends one. `came_from`/`g_scores` are keyed on the full
+    /// state, so the returned path reflects exactly when the powerup is used.
     fn a_star(&mut self) -> Vec<(usize, usize)> {
-        // A* from player to destination directly
-        let path_to_destination = self.a_star_path(self.player, self.destination);
-        // A* from player to powerup to destination
-        let path_to_powerup = self.a_star_path(self.player, self.powerup);
-        let path_from_powerup_to_destination = self.a_star_path(self.powerup, self.destination);
-
-        match path_to_powerup {
-            Some(path_to_powerup) => match path_from_powerup_to_destination {
-                Some(path_from_powerup_to_destination) => {
-                    let mut path = path_to_powerup.clone();
-                    path.extend(path_from_powerup_to_destination);
-                    path
+        let start = self.player;
+        let goal = self.destination;
+        let start_charges = if start == self.powerup.position {
+            self.powerup.value
+        } else {
+            0
+        };
+
+        let mut open_set = BinaryHeap::new();
+        let mut came_from: HashMap<State, State> = HashMap::new();
+        let mut g_scores: HashMap<State, f64> = HashMap::new();
+
+        let start_state = (start, start_charges);
+        g_scores.insert(start_state, 0.0);
+        open_set.push(Node::new(
+            start,
+            start_charges,
+            0.0,
+            self.heuristic(start, goal),
+        ));
+
+        while let Some(current) = open_set.pop() {
+            let state = (current.point, current.charges);
+            // Lazy deletion of superseded duplicates for this exact state.
+            if current.g_score > g_scores[&state] {
+                continue;
+            }
+
+            if current.point == goal {
+                let mut path = vec![current.point];
+                let mut node = state;
+                while let Some(&prev) = came_from.get(&node) {
+                    path.push(prev.0);
+                    node = prev;
                 }
-                None => path_to_destination.expect("No path found"),
-            },
-            None => path_to_destination.expect("No path found"),
+                path.reverse();
+                return path;
+            }
+
+            for neighbor in self.get_neighbors(current.point, current.charges) {
+                // Entering a barrier spends a charge; landing on the powerup
+                // refills them to the full value.
+                let mut new_charges = if self.barriers.contains(&neighbor) {
+                    current.charges - 1
+                } else {
+                    current.charges
+                };
+                if neighbor == self.powerup.position {
+                    new_charges = self.powerup.value;
+                }
+
+                let neighbor_state = (neighbor, new_charges);
+                let tentative_g_score = g_scores[&state] + self.step_cost(current.point, neighbor);
+                if !g_scores.contains_key(&neighbor_state)
+                    || tentative_g_score < g_scores[&neighbor_state]
+                {
+                    g_scores.insert(neighbor_state, tentative_g_score);
+                    came_from.insert(neighbor_state, state);
+                    open_set.push(Node::new(
+                        neighbor,
+                        new_charges,
+                        tentative_g_score,
+                        self.heuristic(neighbor, goal),
+                    ));
+                }
+            }
         }
+
+        panic!("No path found");
+    }
+
+    /// Total cost of walking `path`, summed the same way `a_star_path`
+    /// accumulates `g_score` (terrain multiplier times per-step cost).
+    fn path_cost(&self, path: &[(usize, usize)]) -> f64 {
+        path.windows(2)
+            .map(|w| self.move_cost(w[1]) * self.step_cost(w[0], w[1]))
+            .sum()
+    }
+
+    /// Plan the cheapest tour from the player to the destination that visits a
+    /// set of `waypoints`. When `required` is true every waypoint must be
+    /// visited; otherwise the planner is free to visit only the subset that
+    /// lowers the total cost (possibly none, i.e. the direct path).
+    ///
+    /// Shortest paths between every relevant pair are found with `a_star_path`
+    /// to build a distance matrix, the optimal ordering is solved with a
+    /// Held–Karp bitmask DP, and the chosen legs are stitched into one path.
+    fn a_star_multi(&mut self, waypoints: &[(usize, usize)], required: bool) -> Vec<(usize, usize)> {
+        let mut points = vec![self.player];
+        points.extend_from_slice(waypoints);
+        points.push(self.destination);
+        let n = points.len();
+        let m = waypoints.len();
+        let dest = n - 1;
+
+        // Complete matrix of leg paths and their costs between every pair.
+        let mut legs = vec![vec![None; n]; n];
+        let mut cost = vec![vec![f64::INFINITY; n]; n];
+        for i in 0..n {
+            for j in 0..n {
+                if i == j {
+                    continue;
+                }
+                if let Some(path) = self.a_star_path(points[i], points[j]) {
+                    cost[i][j] = self.path_cost(&path);
+                    legs[i][j] = Some(path);
+                }
+            }
+        }
+
+        // With no waypoints the problem collapses to the direct path.
+        if m == 0 {
+            return legs[0][dest].clone().expect("No path found");
+        }
+
+        // Held–Karp: dp[mask][j] = cheapest cost to start at the player, visit
+        // exactly the waypoints in `mask`, and finish standing on waypoint `j`.
+        let full = 1usize << m;
+        let mut dp = vec![vec![f64::INFINITY; m]; full];
+        let mut parent = vec![vec![usize::MAX; m]; full];
+        for j in 0..m {
+            dp[1 << j][j] = cost[0][j + 1];
+        }
+        for mask in 0..full {
+            for j in 0..m {
+                if mask & (1 << j) == 0 || dp[mask][j].is_infinite() {
+                    continue;
+                }
+                for k in 0..m {
+                    if mask & (1 << k) != 0 {
+                        continue;
+                    }
+                    let next = mask | (1 << k);
+                    let candidate = dp[mask][j] + cost[j + 1][k + 1];
+                    if candidate < dp[next][k] {
+                        dp[next][k] = candidate;
+                        parent[next][k] = j;
+                    }
+                }
+            }
+        }
+
+        // Close the tour at the destination, picking the best end waypoint and
+        // (when waypoints are optional) the best visited subset.
+        let mut best = f64::INFINITY;
+        let mut best_mask = 0usize;
+        let mut best_j = usize::MAX;
+        let masks: Vec<usize> = if required {
+            vec![full - 1]
+        } else {
+            (1..full).collect()
+        };
+        for &mask in &masks {
+            for j in 0..m {
+                if mask & (1 << j) == 0 || dp[mask][j].is_infinite() {
+                    continue;
+                }
+                let total = dp[mask][j] + cost[j + 1][dest];
+                if total < best {
+                    best = total;
+                    best_mask = mask;
+                    best_j = j;
+                }
+            }
+        }
+
+        // When waypoints are optional, the direct path may beat any detour.
+        if !required && cost[0][dest] < best {
+            return legs[0][dest].clone().expect("No path found");
+        }
+        if best_j == usize::MAX {
+            return legs[0][dest].clone().expect("No path found");
+        }
+
+        // Recover the waypoint order by walking the DP parents backwards.
+        let mut order = Vec::new();
+        let mut mask = best_mask;
+        let mut j = best_j;
+        while j != usize::MAX {
+            order.push(j);
+            let prev = parent[mask][j];
+            mask &= !(1 << j);
+            j = prev;
+        }
+        order.reverse();
+
+        // Stitch the legs player -> w0 -> ... -> destination, dropping the
+        // shared junction cell between consecutive legs.
+        let mut seq = vec![0usize];
+        seq.extend(order.iter().map(|&w| w + 1));
+        seq.push(dest);
+        let mut path: Vec<(usize, usize)> = Vec::new();
+        for pair in seq.windows(2) {
+            let leg = legs[pair[0]][pair[1]].clone().expect("No path found");
+            if path.is_empty() {
+                path.extend(leg);
+            } else {
+                path.extend(leg.into_iter().skip(1));
+            }
+        }
+        path
     }
 
     fn a_star_path(
@@ -179,27 +495,35 @@ impl Game {
 
         g_scores.insert(start, 0.0);
 
-        open_set.push(Node::new(start, 0.0, calc_dist(start, goal)));
+        open_set.push(Node::new(start, 0, 0.0, self.heuristic(start, goal)));
 
         while let Some(current) = open_set.pop() {
+            // Lazy deletion: skip superseded duplicates left in the heap by a
+            // later, cheaper relaxation of the same cell.
+            if current.g_score > g_scores[&current.point] {
+                continue;
+            }
+
             if current.point == goal {
                 let mut path = vec![current.point];
                 let mut node = current;
                 while let Some(&prev_point) = came_from.get(&node.point) {
                     path.push(prev_point);
-                    node = Node::new(prev_point, 0.0, 0.0);
+                    node = Node::new(prev_point, 0, 0.0, 0.0);
                 }
                 path.reverse();
                 return Some(path);
             }
 
-            for neighbor in self.get_neighbors(current.point) {
-                let tentative_g_score =
-                    g_scores[&current.point] + calc_dist(current.point, neighbor);
+            // Point-only search: barriers stay traversable-but-expensive via
+            // `move_cost`, so charges are irrelevant here (always available).
+            for neighbor in self.get_neighbors(current.point, usize::MAX) {
+                let tentative_g_score = g_scores[&current.point]
+                    + self.move_cost(neighbor) * self.step_cost(current.point, neighbor);
                 if !g_scores.contains_key(&neighbor) || tentative_g_score < g_scores[&neighbor] {
                     g_scores.insert(neighbor, tentative_g_score);
-                    let h_score = calc_dist(neighbor, goal);
-                    open_set.push(Node::new(neighbor, tentative_g_score, h_score));
+                    let h_score = self.heuristic(neighbor, goal);
+                    open_set.push(Node::new(neighbor, 0, tentative_g_score, h_score));
                     came_from.insert(neighbor, current.point);
                 }
             }
@@ -214,7 +538,17 @@ fn main() {
 
     game.print_board();
 
-    let path = game.a_star();
+    // Optimal tour visiting the powerup as an optional objective.
+    let tour = game.a_star_multi(&[game.powerup.position], false);
+    println!("multi-waypoint tour: {} steps", tour.len() - 1);
 
+    // State-aware plan that may spend the powerup to punch through the barrier.
+    let path = game.a_star();
     game.play(path);
+
+    // A hand-written map solved under uniform (Chebyshev) step costs.
+    let mut custom = Game::from_str("P----\n-xxx-\nO---D").expect("valid map");
+    custom.mode = MovementMode::Uniform;
+    let custom_path = custom.a_star();
+    println!("custom map: {} steps", custom_path.len() - 1);
 }